@@ -2,9 +2,313 @@
 // Use of this source code is governed by a BSD-style license that can be
 // found in the LICENSE file.
 
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use libchromeos::panic_handler::install_memfd_handler;
 
+/// The kind of fault this binary should trigger, selected via `--mode`.
+///
+/// Each variant exercises a distinct signal or crash path so the calling
+/// Tast test can confirm `install_memfd_handler` captures and forwards a
+/// crash report for that class of fault, not only `panic!`.
+enum Mode {
+    /// Rust `panic!`. This is the default, matching the original behavior
+    /// of this binary.
+    Panic,
+    /// `std::process::abort()`, which raises `SIGABRT` without unwinding.
+    Abort,
+    /// Dereference a null pointer, triggering `SIGSEGV`.
+    SegfaultNullDeref,
+    /// Recurse until the stack is exhausted, triggering `SIGSEGV`.
+    StackOverflow,
+    /// `libc::raise(SIGFPE)`.
+    DivideByZero,
+    /// `libc::raise(SIGABRT)`.
+    SigAbort,
+    /// `libc::raise(SIGILL)`.
+    SigIll,
+}
+
+impl Mode {
+    fn parse(s: &str) -> Option<Mode> {
+        match s {
+            "panic" => Some(Mode::Panic),
+            "abort" => Some(Mode::Abort),
+            "segv" => Some(Mode::SegfaultNullDeref),
+            "stack-overflow" => Some(Mode::StackOverflow),
+            "sigfpe" => Some(Mode::DivideByZero),
+            "sigabrt" => Some(Mode::SigAbort),
+            "sigill" => Some(Mode::SigIll),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Mode::Panic => "panic",
+            Mode::Abort => "abort",
+            Mode::SegfaultNullDeref => "segv",
+            Mode::StackOverflow => "stack-overflow",
+            Mode::DivideByZero => "sigfpe",
+            Mode::SigAbort => "sigabrt",
+            Mode::SigIll => "sigill",
+        }
+    }
+}
+
+/// Recurses indefinitely so the stack guard page is hit and the process
+/// receives `SIGSEGV`. The volatile write after the recursive call forces
+/// `buf` to stay live across it, so LLVM can't turn this into a loop the
+/// way it would a plain tail call; `#[inline(never)]` then keeps it from
+/// being inlined away entirely. The recursion is intentionally
+/// unconditional — that's what drives the stack to overflow.
+#[inline(never)]
+#[allow(unconditional_recursion)]
+fn overflow_stack(x: u64) -> u64 {
+    let mut buf = [0u8; 4096];
+    unsafe {
+        std::ptr::write_volatile(buf.as_mut_ptr(), x as u8);
+    }
+    let r = overflow_stack(x.wrapping_add(1));
+    unsafe {
+        std::ptr::write_volatile(buf.as_mut_ptr(), r as u8);
+    }
+    r
+}
+
+fn crash(mode: Mode) -> ! {
+    match mode {
+        Mode::Panic => panic!("See you later, alligator!"),
+        Mode::Abort => std::process::abort(),
+        Mode::SegfaultNullDeref => unsafe {
+            let p: *mut u8 = std::ptr::null_mut();
+            std::ptr::write_volatile(p, 0);
+            unreachable!()
+        },
+        Mode::StackOverflow => {
+            overflow_stack(1);
+            unreachable!()
+        }
+        Mode::DivideByZero => unsafe {
+            libc::raise(libc::SIGFPE);
+            unreachable!()
+        },
+        Mode::SigAbort => unsafe {
+            libc::raise(libc::SIGABRT);
+            unreachable!()
+        },
+        Mode::SigIll => unsafe {
+            libc::raise(libc::SIGILL);
+            unreachable!()
+        },
+    }
+}
+
+/// Appends an `"<iteration> <nonce>\n"` marker to `state_file` before
+/// crashing, so a reboot-stress harness can tell iterations apart.
+fn record_iteration(state_file: &str, iteration: u64) {
+    let nonce = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock before UNIX epoch")
+        .as_nanos();
+
+    let mut f = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(state_file)
+        .expect("failed to open state file");
+    writeln!(f, "{} {}", iteration, nonce).expect("failed to write state file");
+    f.sync_all().expect("failed to sync state file");
+}
+
+/// Default cap applied when `--max-artifact-kb` is not given.
+const DEFAULT_MAX_ARTIFACT_KB: u64 = 64;
+
+/// Header prepended to a truncated artifact, marking it as cut down.
+const ARTIFACT_HEADER: &str = "# truncated: showing most recent bytes\n";
+
+/// Truncates `data` to at most `max_len` bytes, keeping a header plus the
+/// most recent bytes rather than the oldest ones, since the end of a
+/// crash dump matters more than its start. The header itself is clamped
+/// to `max_len` so the result never exceeds it, even for a tiny cap.
+fn truncate_artifact(data: &[u8], max_len: usize) -> Vec<u8> {
+    if data.len() <= max_len {
+        return data.to_vec();
+    }
+    let header = &ARTIFACT_HEADER.as_bytes()[..ARTIFACT_HEADER.len().min(max_len)];
+    let budget = max_len.saturating_sub(header.len());
+    let tail = &data[data.len() - budget.min(data.len())..];
+    let mut out = Vec::with_capacity(header.len() + tail.len());
+    out.extend_from_slice(header);
+    out.extend_from_slice(tail);
+    out
+}
+
+/// Writes `contents` as a new artifact under `dir`, deleting the oldest
+/// existing artifacts first so the directory's total size stays within
+/// `max_kb` kibibytes.
+fn write_bounded_artifact(dir: &str, max_kb: u64, name: &str, contents: &[u8]) {
+    std::fs::create_dir_all(dir).expect("failed to create artifact dir");
+
+    let max_bytes = max_kb.saturating_mul(1024);
+    let contents = truncate_artifact(contents, max_bytes as usize);
+
+    let mut entries: Vec<_> = std::fs::read_dir(dir)
+        .expect("failed to read artifact dir")
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file())
+        .collect();
+    entries.sort_by_key(|e| {
+        e.metadata()
+            .and_then(|m| m.modified())
+            .unwrap_or(UNIX_EPOCH)
+    });
+
+    let mut total: u64 = entries
+        .iter()
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum();
+    for entry in entries {
+        if total + contents.len() as u64 <= max_bytes {
+            break;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        if std::fs::remove_file(entry.path()).is_ok() {
+            total = total.saturating_sub(size);
+        }
+    }
+
+    let path = Path::new(dir).join(name);
+    std::fs::write(&path, &contents).expect("failed to write artifact");
+}
+
+/// Path to the kernel's core dump handler configuration.
+const CORE_PATTERN_PATH: &str = "/proc/sys/kernel/core_pattern";
+
+/// Returns whether `signum` is set in `/proc/self/status`'s `SigCgt` mask.
+fn signal_is_caught(signum: i32) -> bool {
+    let status = match std::fs::read_to_string("/proc/self/status") {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
+    let mask = status
+        .lines()
+        .find_map(|line| line.strip_prefix("SigCgt:"))
+        .and_then(|hex| u64::from_str_radix(hex.trim(), 16).ok())
+        .unwrap_or(0);
+    mask & (1 << (signum - 1)) != 0
+}
+
+/// Returns whether this process holds an open `memfd` fd, i.e. a
+/// `/proc/self/fd` entry whose symlink target starts with `/memfd:`.
+fn memfd_is_allocated() -> bool {
+    let entries = match std::fs::read_dir("/proc/self/fd") {
+        Ok(e) => e,
+        Err(_) => return false,
+    };
+    entries.filter_map(|e| e.ok()).any(|e| {
+        std::fs::read_link(e.path())
+            .map(|target| target.to_string_lossy().starts_with("/memfd:"))
+            .unwrap_or(false)
+    })
+}
+
+/// Reads `core_pattern` and returns its trimmed value, together with
+/// whether it pipes core dumps to a collector program (a value starting
+/// with `|`) that exists on disk.
+fn core_pattern_collector() -> (String, bool) {
+    let pattern = std::fs::read_to_string(CORE_PATTERN_PATH)
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    let reachable = pattern
+        .strip_prefix('|')
+        .and_then(|rest| rest.split_whitespace().next())
+        .map(|program| Path::new(program).exists())
+        .unwrap_or(false);
+    (pattern, reachable)
+}
+
+/// Escapes `s` for embedding in a JSON string literal.
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Runs `--probe` mode: reports the crash-collection pipeline's runtime
+/// state as a JSON object on stdout, instead of crashing.
+fn probe() {
+    let handler_installed =
+        signal_is_caught(libc::SIGSEGV) && signal_is_caught(libc::SIGABRT);
+    let memfd_allocated = memfd_is_allocated();
+    let (core_pattern, collector_reachable) = core_pattern_collector();
+
+    println!(
+        "{{\"handler_installed\":{},\"memfd_allocated\":{},\"collector_reachable\":{},\"core_pattern\":\"{}\"}}",
+        handler_installed,
+        memfd_allocated,
+        collector_reachable,
+        json_escape(&core_pattern),
+    );
+}
+
 fn main() -> Result<(), ()> {
     install_memfd_handler();
-    panic!("See you later, alligator!")
+
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.iter().any(|arg| arg == "--probe") {
+        probe();
+        return Ok(());
+    }
+
+    let mode = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--mode=").map(str::to_string))
+        .and_then(|s| Mode::parse(&s))
+        .unwrap_or(Mode::Panic);
+
+    let iteration = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--iteration=").map(str::to_string))
+        .and_then(|s| s.parse::<u64>().ok());
+    let state_file = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--state-file=").map(str::to_string));
+
+    if let (Some(iteration), Some(state_file)) = (iteration, &state_file) {
+        record_iteration(state_file, iteration);
+    }
+
+    let artifact_dir = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--artifact-dir=").map(str::to_string));
+    if let Some(artifact_dir) = artifact_dir {
+        let max_artifact_kb = args
+            .iter()
+            .find_map(|arg| arg.strip_prefix("--max-artifact-kb=").map(str::to_string))
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_MAX_ARTIFACT_KB);
+
+        let nonce = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock before UNIX epoch")
+            .as_nanos();
+        let name = format!("crash-{}.log", nonce);
+        let contents = format!("mode={}\niteration={:?}\n", mode.name(), iteration);
+        write_bounded_artifact(&artifact_dir, max_artifact_kb, &name, contents.as_bytes());
+    }
+
+    crash(mode)
 }